@@ -0,0 +1,145 @@
+//! An incremental lint cache, keyed on the commit range, a file's changed
+//! lines and current bytes, plus the resolved linter config (cmd+args+regex)
+//! and the linter binary's mtime. Lets repeated runs over the same commit
+//! range skip linters whose inputs haven't changed since the last run.
+
+use lint_emit::{DiffMeta, LinterConfig, LintMessage};
+use failure::Error;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::fs;
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Cache {
+    entries: HashMap<String, CacheEntry>
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    file: PathBuf,
+    messages: Vec<LintMessage>
+}
+
+impl Cache {
+    /// Load the cache from `path`, or start with an empty one if it doesn't
+    /// exist yet or fails to parse
+    pub fn load(path: &PathBuf) -> Cache {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|raw| toml::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &PathBuf) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Drop entries whose source file no longer exists on disk
+    pub fn prune(&mut self) {
+        self.entries.retain(|_, entry| entry.file.exists());
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<LintMessage>> {
+        self.entries.get(key).map(|entry| entry.messages.clone())
+    }
+
+    pub fn put(&mut self, key: String, file: PathBuf, messages: Vec<LintMessage>) {
+        self.entries.insert(key, CacheEntry { file, messages });
+    }
+}
+
+/// Compute the cache key for a file under a set of linters: a hash of the
+/// commit range, the file's changed line numbers and current bytes, and each
+/// linter's cmd/args/regex/severity_map and binary mtime. Any change to these
+/// invalidates the cache automatically, since the key simply changes.
+pub fn cache_key(commit_range: &str, diff_meta: &DiffMeta, linters: &[&LinterConfig]) -> Result<String, Error> {
+    let mut hasher = DefaultHasher::new();
+
+    commit_range.hash(&mut hasher);
+    diff_meta.changed_line_numbers().hash(&mut hasher);
+
+    let bytes = fs::read(&diff_meta.file)?;
+    bytes.hash(&mut hasher);
+
+    for linter in linters {
+        linter.name.hash(&mut hasher);
+        linter.cmd.hash(&mut hasher);
+        linter.args.hash(&mut hasher);
+        linter.regex.hash(&mut hasher);
+        hash_severity_map(&linter.severity_map, &mut hasher);
+
+        // A bare command name is looked up on PATH, as `Command` does
+        let cmd_path = PathBuf::from(&linter.cmd);
+        let binary = if cmd_path.components().count() > 1 {
+            Some(cmd_path)
+        } else {
+            std::env::var_os("PATH").and_then(|paths| {
+                std::env::split_paths(&paths)
+                    .map(|dir| dir.join(&linter.cmd))
+                    .find(|candidate| candidate.is_file())
+            })
+        };
+        if let Some(modified) = binary.and_then(|binary| fs::metadata(binary).ok()).and_then(|metadata| metadata.modified().ok()) {
+            modified.hash(&mut hasher);
+        }
+    }
+
+    Ok(format!("{:x}", hasher.finish()))
+}
+
+/// `HashMap` has no `Hash` impl (its iteration order isn't stable), so hash a
+/// sorted snapshot of its entries instead
+fn hash_severity_map(severity_map: &Option<HashMap<String, String>>, hasher: &mut DefaultHasher) {
+    if let Some(severity_map) = severity_map {
+        let mut entries: Vec<(&String, &String)> = severity_map.iter().collect();
+        entries.sort_by_key(|(raw, _)| raw.to_owned());
+        entries.hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linter_config(name: &str, severity_map: Option<HashMap<String, String>>) -> LinterConfig {
+        LinterConfig {
+            name: name.to_owned(),
+            cmd: "true".to_owned(),
+            args: vec![],
+            regex: ".*".to_owned(),
+            ext: vec!["rs".to_owned()],
+            include: None,
+            exclude: None,
+            severity_map
+        }
+    }
+
+    #[test]
+    fn cache_key_changes_with_linter_config_and_severity_map() {
+        let path = std::env::temp_dir().join(format!("lint-emit-cache-key-test-{}", std::process::id()));
+        fs::write(&path, b"fn main() {}").unwrap();
+        let diff_meta = DiffMeta::at_lines(path.clone(), vec![1]);
+
+        let plain = linter_config("clippy", None);
+        let renamed = linter_config("clippy-strict", None);
+        let mut strict_severity_map = HashMap::new();
+        strict_severity_map.insert("warn".to_owned(), "error".to_owned());
+        let mapped = linter_config("clippy", Some(strict_severity_map));
+
+        let key_plain = cache_key("HEAD^..HEAD", &diff_meta, &[&plain]).unwrap();
+        let key_renamed = cache_key("HEAD^..HEAD", &diff_meta, &[&renamed]).unwrap();
+        let key_mapped = cache_key("HEAD^..HEAD", &diff_meta, &[&mapped]).unwrap();
+
+        assert_ne!(key_plain, key_renamed);
+        assert_ne!(key_plain, key_mapped);
+
+        fs::remove_file(&path).ok();
+    }
+}
@@ -1,32 +1,313 @@
-use super::LintMessage;
+use super::{LintMessage, Severity};
 use itertools::Itertools;
 use colored::*;
 use walkdir::WalkDir;
 use std::path::PathBuf;
+use serde::Serialize;
+use syntect::parsing::SyntaxSet;
+use syntect::highlighting::ThemeSet;
+use syntect::easy::HighlightLines;
+use syntect::util::as_24_bit_terminal_escaped;
 
-pub fn render(lint_messages: Vec<LintMessage>) {
+/// The output format used by `render`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Colored, human-oriented text (the default)
+    Pretty,
+    /// A flat JSON array of lint messages
+    Json,
+    /// A SARIF 2.1.0 document, for consumption by other tooling
+    Sarif,
+    /// GitHub Actions workflow-command annotations
+    Github
+}
+
+impl OutputFormat {
+    /// Parse an `OutputFormat` from the `--format` CLI value
+    pub fn from_str(format: &str) -> Option<OutputFormat> {
+        match format {
+            "pretty" => Some(OutputFormat::Pretty),
+            "json" => Some(OutputFormat::Json),
+            "sarif" => Some(OutputFormat::Sarif),
+            "github" => Some(OutputFormat::Github),
+            _ => None
+        }
+    }
+}
+
+/// Whether source snippets should be syntax-highlighted before printing.
+/// Honors `--no-highlight`, `NO_COLOR`, and whether stdout is a TTY.
+pub fn should_highlight(no_highlight_flag: bool) -> bool {
+    if no_highlight_flag || std::env::var_os("NO_COLOR").is_some() {
+        return false;
+    }
+    atty::is(atty::Stream::Stdout)
+}
+
+/// A pluggable sink for lint messages, selected by `--format`
+pub trait Reporter {
+    fn report(&self, lint_messages: Vec<LintMessage>);
+}
+
+pub struct PrettyReporter {
+    pub highlight: bool
+}
+
+impl Reporter for PrettyReporter {
+    fn report(&self, lint_messages: Vec<LintMessage>) {
+        render_pretty(lint_messages, self.highlight)
+    }
+}
+
+pub struct JsonReporter;
+
+impl Reporter for JsonReporter {
+    fn report(&self, lint_messages: Vec<LintMessage>) {
+        render_json(lint_messages)
+    }
+}
+
+pub struct SarifReporter;
+
+impl Reporter for SarifReporter {
+    fn report(&self, lint_messages: Vec<LintMessage>) {
+        render_sarif(lint_messages)
+    }
+}
+
+pub struct GithubReporter;
+
+impl Reporter for GithubReporter {
+    fn report(&self, lint_messages: Vec<LintMessage>) {
+        crate::github::render(&lint_messages)
+    }
+}
+
+/// Build the `Reporter` selected by `--format` and hand it the lint messages
+pub fn render(lint_messages: Vec<LintMessage>, format: OutputFormat, highlight: bool) {
+    let reporter: Box<dyn Reporter> = match format {
+        OutputFormat::Pretty => Box::new(PrettyReporter { highlight }),
+        OutputFormat::Json => Box::new(JsonReporter),
+        OutputFormat::Sarif => Box::new(SarifReporter),
+        OutputFormat::Github => Box::new(GithubReporter)
+    };
+    reporter.report(lint_messages);
+}
+
+fn render_pretty(lint_messages: Vec<LintMessage>, highlight: bool) {
     if lint_messages.len() == 0 {
         println!("{}", "No errors found! Nice.".green().bold());
     }
+
+    // Load these once for the whole render, rather than per line
+    let syntax_set = SyntaxSet::load_defaults_newlines();
+    let theme_set = ThemeSet::load_defaults();
+    let theme = &theme_set.themes["base16-ocean.dark"];
+
     // Group the ouput by file
     for (file, outputs) in &lint_messages.into_iter().group_by(|elt| elt.file.to_owned()) {
         let project_root = get_project_root(&file);
         let file_name = file.strip_prefix(&project_root).unwrap().to_str().unwrap();
+        let syntax = file
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| syntax_set.find_syntax_by_extension(ext));
         outputs
             .group_by(|lint_message| lint_message.line)
             .into_iter()
             .for_each(|(line, lint_messages)| {
                 println!("{}:{}", file_name.green(), line.to_string().dimmed());
-                print_lint_message(lint_messages.collect(), line);
+                print_lint_message(lint_messages.collect(), line, highlight, syntax, &syntax_set, theme);
             });
     }
 }
 
+/// Highlight `source` according to `syntax`, falling back to the plain string
+/// when highlighting is disabled or no syntax matches the file extension
+fn highlight_source(
+    source: &str,
+    highlight: bool,
+    syntax: Option<&syntect::parsing::SyntaxReference>,
+    syntax_set: &SyntaxSet,
+    theme: &syntect::highlighting::Theme
+) -> String {
+    let syntax = match (highlight, syntax) {
+        (true, Some(syntax)) => syntax,
+        _ => return source.to_owned()
+    };
+
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    match highlighter.highlight(source, syntax_set) {
+        Ok(ranges) => as_24_bit_terminal_escaped(&ranges[..], false),
+        Err(_) => source.to_owned()
+    }
+}
+
+#[derive(Serialize)]
+struct JsonLintMessage<'a> {
+    linter: &'a str,
+    file: String,
+    line: u32,
+    message: &'a str,
+    severity: Severity
+}
+
+/// Emit every `LintMessage` as a single, stable JSON array on stdout
+fn render_json(lint_messages: Vec<LintMessage>) {
+    let messages: Vec<JsonLintMessage> = lint_messages
+        .iter()
+        .map(|lint_message| JsonLintMessage {
+            linter: &lint_message.linter,
+            file: repo_relative_path(&lint_message.file),
+            line: lint_message.line,
+            message: &lint_message.message,
+            severity: lint_message.severity
+        })
+        .collect();
+
+    println!("{}", serde_json::to_string_pretty(&messages).unwrap());
+}
+
+#[derive(Serialize)]
+struct SarifLog {
+    version: &'static str,
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    runs: Vec<SarifRun>
+}
+
+#[derive(Serialize)]
+struct SarifRun {
+    tool: SarifTool,
+    results: Vec<SarifResult>
+}
+
+#[derive(Serialize)]
+struct SarifTool {
+    driver: SarifDriver
+}
+
+#[derive(Serialize)]
+struct SarifDriver {
+    name: &'static str,
+    rules: Vec<SarifRule>
+}
+
+#[derive(Serialize)]
+struct SarifRule {
+    id: String
+}
+
+#[derive(Serialize)]
+struct SarifResult {
+    #[serde(rename = "ruleId")]
+    rule_id: String,
+    level: &'static str,
+    message: SarifMessage,
+    locations: Vec<SarifLocation>
+}
+
+#[derive(Serialize)]
+struct SarifMessage {
+    text: String
+}
+
+#[derive(Serialize)]
+struct SarifLocation {
+    #[serde(rename = "physicalLocation")]
+    physical_location: SarifPhysicalLocation
+}
+
+#[derive(Serialize)]
+struct SarifPhysicalLocation {
+    #[serde(rename = "artifactLocation")]
+    artifact_location: SarifArtifactLocation,
+    region: SarifRegion
+}
+
+#[derive(Serialize)]
+struct SarifArtifactLocation {
+    uri: String
+}
+
+#[derive(Serialize)]
+struct SarifRegion {
+    #[serde(rename = "startLine")]
+    start_line: u32
+}
+
+/// Map a `Severity` to the SARIF `level` vocabulary
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "note"
+    }
+}
+
+/// Serialize the lint messages as a SARIF 2.1.0 log, suitable for GitHub code
+/// scanning and other SARIF consumers
+fn render_sarif(lint_messages: Vec<LintMessage>) {
+    let rules: Vec<SarifRule> = lint_messages
+        .iter()
+        .map(|lint_message| lint_message.linter.to_owned())
+        .unique()
+        .map(|linter| SarifRule { id: linter })
+        .collect();
+
+    let results: Vec<SarifResult> = lint_messages
+        .iter()
+        .map(|lint_message| SarifResult {
+            rule_id: lint_message.linter.to_owned(),
+            level: sarif_level(lint_message.severity),
+            message: SarifMessage { text: lint_message.message.to_owned() },
+            locations: vec![SarifLocation {
+                physical_location: SarifPhysicalLocation {
+                    artifact_location: SarifArtifactLocation {
+                        uri: repo_relative_path(&lint_message.file)
+                    },
+                    region: SarifRegion { start_line: lint_message.line }
+                }
+            }]
+        })
+        .collect();
+
+    let log = SarifLog {
+        version: "2.1.0",
+        schema: "https://raw.githubusercontent.com/oasis-tcs/sarif-spec/master/Schemata/sarif-schema-2.1.0.json",
+        runs: vec![SarifRun {
+            tool: SarifTool {
+                driver: SarifDriver {
+                    name: "lint-emit",
+                    rules
+                }
+            },
+            results
+        }]
+    };
+
+    println!("{}", serde_json::to_string_pretty(&log).unwrap());
+}
+
+/// Return `file` as a path relative to the repo root, for use in machine-readable output
+pub(crate) fn repo_relative_path(file: &PathBuf) -> String {
+    let project_root = get_project_root(file);
+    file.strip_prefix(&project_root).unwrap().to_str().unwrap().to_owned()
+}
+
 /// Print the lint message to stdout
-fn print_lint_message(lint_messages: Vec<LintMessage>, line: u32) {
+fn print_lint_message(
+    lint_messages: Vec<LintMessage>,
+    line: u32,
+    highlight: bool,
+    syntax: Option<&syntect::parsing::SyntaxReference>,
+    syntax_set: &SyntaxSet,
+    theme: &syntect::highlighting::Theme
+) {
     let line_string = line.to_string();
     let padding = &str::repeat(" ", line_string.len());
     let source = &lint_messages.first().unwrap().source;
+    let source = highlight_source(source, highlight, syntax, syntax_set, theme);
     let vertical_line = format!("{} {}", padding, "|".blue());
     println!("{}", vertical_line);
     println!("{} {} {}", line_string.blue(), "|".blue(), source);
@@ -41,15 +322,22 @@ fn print_lint_message(lint_messages: Vec<LintMessage>, line: u32) {
             lint_messages
                 .into_iter()
                 .for_each(|lint_message| {
-                    let message = lint_message.message;
-                    let linter = lint_message.linter;
-                    println!("{} {}", "-->".blue(), message.bold());
+                    println!("{} {} {}", "-->".blue(), severity_label(lint_message.severity), lint_message.message.bold());
                 });
         });
     println!("");
 }
 
 
+/// A colored, bracketed label for a severity, e.g. `[error]`
+fn severity_label(severity: Severity) -> ColoredString {
+    match severity {
+        Severity::Error => "[error]".red().bold(),
+        Severity::Warning => "[warning]".yellow().bold(),
+        Severity::Info => "[info]".blue().bold()
+    }
+}
+
 /// Recrsively looks for a parent directory containing .git and returns the path
 fn get_project_root(file: &PathBuf) -> PathBuf {
     for entry in WalkDir::new(&file)
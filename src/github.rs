@@ -0,0 +1,119 @@
+//! A reporter for GitHub Actions: workflow-command annotations for `--format github`,
+//! and an optional inline PR review via `--github-review`.
+
+use lint_emit::{LintMessage, Severity};
+use crate::display;
+use failure::Error;
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+use serde::Serialize;
+
+/// Print one `::{level} file=...,line=...::{linter}: {message}` annotation per
+/// `LintMessage`, so findings surface inline in the GitHub PR diff UI
+pub fn render(lint_messages: &[LintMessage]) {
+    for lint_message in lint_messages {
+        let command = match lint_message.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "notice"
+        };
+        let file = repo_relative_path(&lint_message.file);
+        println!(
+            "::{} file={},line={}::{}: {}",
+            command, file, lint_message.line, lint_message.linter, lint_message.message
+        );
+    }
+}
+
+#[derive(Serialize)]
+struct ReviewComment {
+    path: String,
+    line: u32,
+    body: String
+}
+
+#[derive(Serialize)]
+struct ReviewRequest {
+    event: &'static str,
+    comments: Vec<ReviewComment>
+}
+
+/// Batch the lint messages into a single PR review via the GitHub API.
+///
+/// Requires `GITHUB_TOKEN` to be set; the owner/repo are derived from the
+/// `origin` git remote and the PR number from `pr_number` or `GITHUB_REF`.
+/// When no token is present, this degrades gracefully to plain annotations.
+pub fn post_review(lint_messages: &[LintMessage], pr_number: Option<u64>, logger: &slog::Logger) -> Result<(), Error> {
+    let token = match env::var("GITHUB_TOKEN") {
+        Ok(token) => token,
+        Err(_) => {
+            slog::warn!(logger, "GITHUB_TOKEN not set, falling back to plain annotations");
+            render(lint_messages);
+            return Ok(());
+        }
+    };
+
+    let (owner, repo) = get_owner_and_repo()?;
+    let pr_number = pr_number
+        .or_else(get_pr_number_from_ref)
+        .expect("No PR number given via --pr-number or GITHUB_REF");
+
+    let comments: Vec<ReviewComment> = lint_messages
+        .iter()
+        .map(|lint_message| ReviewComment {
+            path: repo_relative_path(&lint_message.file),
+            line: lint_message.line,
+            body: format!("**{}**: {}", lint_message.linter, lint_message.message)
+        })
+        .collect();
+
+    let request = ReviewRequest { event: "COMMENT", comments };
+
+    let url = format!("https://api.github.com/repos/{}/{}/pulls/{}/reviews", owner, repo, pr_number);
+    let client = reqwest::blocking::Client::new();
+    client
+        .post(&url)
+        .bearer_auth(token)
+        .header("User-Agent", "lint-emit")
+        .json(&request)
+        .send()?;
+
+    Ok(())
+}
+
+/// Parse `owner/repo` out of the `origin` remote URL
+fn get_owner_and_repo() -> Result<(String, String), Error> {
+    let output = Command::new("git")
+        .arg("remote")
+        .arg("get-url")
+        .arg("origin")
+        .output()?;
+
+    if !output.status.success() {
+        return Err(failure::err_msg("No 'origin' git remote found; cannot determine owner/repo for --github-review"));
+    }
+
+    let remote = String::from_utf8(output.stdout)?.trim().to_owned();
+    let remote = remote.trim_end_matches(".git");
+    let slug = remote
+        .rsplitn(3, |c| c == '/' || c == ':')
+        .take(2)
+        .collect::<Vec<&str>>();
+
+    let repo = slug.get(0).ok_or_else(|| failure::err_msg("Unable to parse repo from origin remote"))?.to_string();
+    let owner = slug.get(1).ok_or_else(|| failure::err_msg("Unable to parse owner from origin remote"))?.to_string();
+    Ok((owner, repo))
+}
+
+/// Parse the PR number out of `GITHUB_REF` (e.g. `refs/pull/123/merge`)
+fn get_pr_number_from_ref() -> Option<u64> {
+    let github_ref = env::var("GITHUB_REF").ok()?;
+    let parts: Vec<&str> = github_ref.split('/').collect();
+    let index = parts.iter().position(|part| *part == "pull")?;
+    parts.get(index + 1)?.parse().ok()
+}
+
+fn repo_relative_path(file: &PathBuf) -> String {
+    display::repo_relative_path(file)
+}
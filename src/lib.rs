@@ -45,12 +45,14 @@
 use regex::Regex;
 use std::process::Command;
 use std::path::PathBuf;
+use std::collections::HashMap;
 use slog::{trace};
 use std::fs;
 use failure::Error;
 use failure::Fail;
 use regex::NoExpand;
 use serde::{Serialize, Deserialize};
+use glob::Pattern;
 
 /// Contains config of the linter
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -59,7 +61,43 @@ pub struct LinterConfig {
     pub cmd: String,
     pub args: Vec<String>,
     pub regex: String,
-    pub ext: Vec<String>
+    pub ext: Vec<String>,
+    /// Glob patterns a path must match at least one of, in addition to `ext`
+    pub include: Option<Vec<String>>,
+    /// Glob patterns which exclude a path from this linter even if `ext`/`include` match
+    pub exclude: Option<Vec<String>>,
+    /// Maps this linter's raw severity tokens (e.g. `"warn"`, `"E"`) to a normalized
+    /// `Severity` name (`"error"`, `"warning"`, `"info"`), overriding the built-in guess
+    pub severity_map: Option<HashMap<String, String>>
+}
+
+/// Returns whether `path` should be linted, given a global and/or per-linter
+/// set of glob `include`/`exclude` patterns. Exclude always wins over include.
+pub fn is_path_allowed(path: &str, include: &Option<Vec<String>>, exclude: &Option<Vec<String>>) -> bool {
+    if let Some(exclude) = exclude {
+        let excluded = exclude.iter().any(|pattern| glob_matches(pattern, path));
+        if excluded {
+            return false;
+        }
+    }
+
+    match include {
+        Some(include) if !include.is_empty() => {
+            include.iter().any(|pattern| glob_matches(pattern, path))
+        },
+        _ => true
+    }
+}
+
+/// Match `path` against `pattern`. A leading `!` negates the match, e.g.
+/// `!tests/**` matches any path that is *not* under `tests/`.
+fn glob_matches(pattern: &str, path: &str) -> bool {
+    match pattern.strip_prefix('!') {
+        Some(negated) => !glob_matches(negated, path),
+        None => Pattern::new(pattern)
+            .map(|pattern| pattern.matches(path))
+            .unwrap_or(false)
+    }
 }
 
 /// Contains the line numbers which have changed for a given file
@@ -69,6 +107,26 @@ pub struct DiffMeta {
     changed_lines: Vec<LineMeta>
 }
 
+impl DiffMeta {
+    /// Construct a `DiffMeta` directly from a set of changed line numbers,
+    /// for callers that don't have a unified diff to parse (e.g. tests)
+    pub fn at_lines(file: PathBuf, changed_lines: Vec<u32>) -> DiffMeta {
+        DiffMeta {
+            file,
+            changed_lines: changed_lines
+                .into_iter()
+                .map(|line| LineMeta { line, source: String::new() })
+                .collect()
+        }
+    }
+
+    /// The line numbers considered "changed" for this file, e.g. for hashing
+    /// into a cache key that must vary with the commit range being linted
+    pub fn changed_line_numbers(&self) -> Vec<u32> {
+        self.changed_lines.iter().map(|line| line.line).collect()
+    }
+}
+
 /// Contains the changed lines and the snippets
 #[derive(Debug)]
 struct LineMeta {
@@ -76,18 +134,70 @@ struct LineMeta {
     source: String
 }
 
+/// How severe a lint message is. Derived from the linter's `severity` capture
+/// group, defaulting to `Warning` when the linter doesn't report one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error
+}
+
+impl Severity {
+    /// Map a linter's raw severity token (e.g. `"error"`, `"warn"`) to a `Severity`,
+    /// defaulting to `Warning` for anything unrecognized
+    fn parse(raw: &str) -> Severity {
+        match raw.to_lowercase().as_str() {
+            "error" | "fatal" => Severity::Error,
+            "info" | "note" | "hint" => Severity::Info,
+            _ => Severity::Warning
+        }
+    }
+
+    pub fn from_str(raw: &str) -> Option<Severity> {
+        match raw.to_lowercase().as_str() {
+            "error" => Some(Severity::Error),
+            "warning" => Some(Severity::Warning),
+            "info" => Some(Severity::Info),
+            _ => None
+        }
+    }
+}
+
+/// Resolve a linter's raw severity token to a `Severity`, consulting its
+/// `severity_map` first and falling back to the built-in heuristic
+fn resolve_severity(raw: &str, severity_map: &Option<HashMap<String, String>>) -> Severity {
+    let mapped = severity_map
+        .as_ref()
+        .and_then(|map| map.get(raw))
+        .and_then(|normalized| Severity::from_str(normalized));
+
+    mapped.unwrap_or_else(|| Severity::parse(raw))
+}
+
 /// Contains the lint message for a given file
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LintMessage {
     pub linter: String,
     pub file: PathBuf,
     pub line: u32,
     pub source: String,
-    pub message: String
+    pub message: String,
+    pub severity: Severity
 }
 
 /// Return the output from running a linter on the whole project
-pub fn get_lint_messages(linters: &Vec<&LinterConfig>, diff_meta: &DiffMeta, logger: &slog::Logger) -> Result<Vec<LintMessage>, Error> {
+///
+/// When `fail_fast` is set and a linter produces a message at or above
+/// `fail_level`, remaining linters for this file are skipped.
+pub fn get_lint_messages(
+    linters: &Vec<&LinterConfig>,
+    diff_meta: &DiffMeta,
+    logger: &slog::Logger,
+    fail_fast: bool,
+    fail_level: Severity
+) -> Result<Vec<LintMessage>, Error> {
     let mut lint_messages: Vec<LintMessage> = vec![];
     for linter in linters.into_iter() {
         let re = Regex::new(&linter.regex)?;
@@ -97,7 +207,11 @@ pub fn get_lint_messages(linters: &Vec<&LinterConfig>, diff_meta: &DiffMeta, log
         trace!(logger, "Capture = {:#?}", cap);
             if let Some(lint_message) = get_lint_message(&linter, cap, diff_meta, logger) {
                 trace!(logger, "Adding = {:#?}", lint_message);
+                let qualifies = lint_message.severity >= fail_level;
                 lint_messages.push(lint_message);
+                if fail_fast && qualifies {
+                    return Ok(lint_messages);
+                }
             }
         }
     }
@@ -119,6 +233,11 @@ fn get_lint_message(linter: &LinterConfig, cap: regex::Captures, diff_meta: &Dif
     let line = cap.name("line")?.as_str().parse::<u32>().unwrap();
     trace!(logger, "Processing line {:?}", line);
 
+    let severity = cap.name("severity")
+        .map(|severity| severity.as_str())
+        .map(|raw| resolve_severity(raw, &linter.severity_map))
+        .unwrap_or(Severity::Warning);
+
     let line_meta = diff_meta.changed_lines.iter().find(|x| x.line == line);
 
     // Filter here
@@ -130,7 +249,8 @@ fn get_lint_message(linter: &LinterConfig, cap: regex::Captures, diff_meta: &Dif
                 source: line_meta.unwrap().source.to_owned(),
                 message,
                 file,
-                line
+                line,
+                severity
             })
         }
     None
@@ -164,59 +284,92 @@ fn get_lint_output(linter: &LinterConfig, file: &PathBuf) -> Result<String, Erro
     Ok(String::from_utf8(result)?)
 }
 
-/// Return the line number for lines which have changed from `git diff`
-fn get_changed_lines_from_diff(hunk: String) -> Result<Vec<LineMeta>, Error> {
-    let mut line_number = 0;
-    let re = Regex::new(r"\+([0-9]+)")?;
+/// Parse a full, multi-file unified diff into a `DiffMeta` per file in a single pass,
+/// rather than re-running `git diff` once per file
+fn parse_diff(diff: &str) -> Result<HashMap<PathBuf, DiffMeta>, Error> {
+    let new_file_re = Regex::new(r"^\+\+\+ b/(.*)$")?;
+    let hunk_re = Regex::new(r"\+([0-9]+)")?;
     let sanitize = Regex::new(r"^[-+ ]\s*")?;
-    let changed_lines = hunk.lines().fold(vec![], |mut changed_lines, line| {
+
+    let mut diff_metas: HashMap<PathBuf, DiffMeta> = HashMap::new();
+    let mut current_file: Option<PathBuf> = None;
+    let mut line_number: u32 = 0;
+
+    for line in diff.lines() {
+        if line.starts_with("diff --git") {
+            // A new file section is starting; the path is finalized on the
+            // following `+++ b/` line
+            current_file = None;
+            continue;
+        }
+
+        if let Some(cap) = new_file_re.captures(line) {
+            let file = fs::canonicalize(&cap[1]).unwrap_or_else(|_| PathBuf::from(&cap[1]));
+            diff_metas.entry(file.clone()).or_insert_with(|| DiffMeta {
+                file: file.clone(),
+                changed_lines: vec![]
+            });
+            current_file = Some(file);
+            continue;
+        }
+
         if line.starts_with("@@") {
-            // This is the line where the diff starts
-            // So lets get the line number
-            let start = re.find(&line).unwrap().as_str();
+            let start = hunk_re.find(&line).unwrap().as_str();
             line_number = start.parse().unwrap();
             line_number -= 1;
-            return changed_lines;
+            continue;
         }
 
+        let file = match &current_file {
+            Some(file) => file,
+            None => continue
+        };
+
         if !line.starts_with('-') {
-            // Increment the current line number if the line wasn't removed
             line_number += 1;
             if line.starts_with('+') {
-                // Sanitize the line
                 let source = sanitize.replace(line, "");
-
-                // Add the line number of the line which was added
-                changed_lines.push(LineMeta {
-                    line: line_number,
-                    source: source.to_string()
-                });
-                return changed_lines;
+                if let Some(diff_meta) = diff_metas.get_mut(file) {
+                    diff_meta.changed_lines.push(LineMeta {
+                        line: line_number,
+                        source: source.to_string()
+                    });
+                }
             }
         }
-        changed_lines
-    });
-    Ok(changed_lines)
+    }
+
+    Ok(diff_metas)
+}
+
+/// Run `git diff` once for the whole commit range and return the changed lines
+/// for every touched file, keyed by path
+pub fn get_all_changed_lines(commit_range: &str) -> Result<HashMap<PathBuf, DiffMeta>, Error> {
+    let diff = get_full_diff(commit_range)?;
+    parse_diff(&diff)
 }
 
-/// Returns the changed line numbers, split by file path
+/// Returns the changed line numbers for a single file
+///
+/// This delegates to [`get_all_changed_lines`] under the hood; prefer calling
+/// that directly when processing more than one file, to avoid re-running `git diff`.
 pub fn get_changed_lines(commit_range: &str, file: &PathBuf) -> Result<DiffMeta, Error> {
-    let diff = get_diff(commit_range, &file)?;
-    let changed_lines = get_changed_lines_from_diff(diff)?;
-    let result = DiffMeta {
-        file: file.to_path_buf(),
-        changed_lines
-    };
+    let mut diff_metas = get_all_changed_lines(commit_range)?;
+    let result = diff_metas
+        .remove(file)
+        .unwrap_or_else(|| DiffMeta {
+            file: file.to_path_buf(),
+            changed_lines: vec![]
+        });
 
     Ok(result)
 }
 
-/// Return the output of `git diff`
-fn get_diff(commit_range: &str, file: &PathBuf) -> Result<String, Error> {
+/// Return the output of `git diff` for the whole commit range
+fn get_full_diff(commit_range: &str) -> Result<String, Error> {
     let output = Command::new("git")
         .arg("diff")
         .arg(commit_range)
-        .arg(file)
         .output()?;
 
     Ok(String::from_utf8(output.stdout)?)
@@ -258,3 +411,40 @@ pub enum LintError {
     #[fail(display = "Parsing error")]
     Parse
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parse_diff;
+    use std::path::PathBuf;
+
+    #[test]
+    fn parse_diff_tracks_added_lines_per_file_across_hunk_boundaries() {
+        let diff = "\
+diff --git a/src/a.rs b/src/a.rs
+index 1111111..2222222 100644
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,3 +1,4 @@
+ fn main() {
++    let x = 1;
+     println!(\"hi\");
+ }
+diff --git a/src/b.rs b/src/b.rs
+index 3333333..4444444 100644
+--- a/src/b.rs
++++ b/src/b.rs
+@@ -10,2 +10,3 @@
+ fn foo() {
++    let y = 2;
+ }
+";
+
+        let diff_metas = parse_diff(diff).unwrap();
+
+        let a = diff_metas.get(&PathBuf::from("src/a.rs")).unwrap();
+        assert_eq!(a.changed_line_numbers(), vec![2]);
+
+        let b = diff_metas.get(&PathBuf::from("src/b.rs")).unwrap();
+        assert_eq!(b.changed_line_numbers(), vec![11]);
+    }
+}
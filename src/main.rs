@@ -49,15 +49,22 @@ extern crate slog_async;
 extern crate itertools;
 extern crate walkdir;
 extern crate serde;
+extern crate serde_json;
 extern crate dialoguer;
 extern crate xdg;
 extern crate toml;
+extern crate syntect;
+extern crate atty;
+extern crate reqwest;
+extern crate notify;
 
 mod display;
+mod github;
+mod cache;
 
 use clap::{Arg, App, AppSettings};
 use std::process::{Command, Stdio};
-use slog::{Level, Logger, Drain, info, debug, trace, o};
+use slog::{Level, Logger, Drain, info, debug, trace, warn, o};
 use slog_term::{TermDecorator, CompactFormat};
 use failure::Error;
 use lint_emit::*;
@@ -66,71 +73,142 @@ use rayon::prelude::*;
 use colored::*;
 use itertools::*;
 use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use cache::Cache;
 use dialoguer::{theme::ColorfulTheme, Checkboxes};
 use serde::{Serialize, Deserialize};
 use std::io::Write;
+use display::OutputFormat;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Config {
-    linters: Option<Vec<LinterConfig>>
+    linters: Option<Vec<LinterConfig>>,
+    profiles: Option<std::collections::HashMap<String, Vec<String>>>,
+    filters: Option<Filters>
+}
+
+/// Global glob `include`/`exclude` filters, applied to every linter in addition
+/// to its own `LinterConfig.include`/`exclude`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct Filters {
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>
+}
+
+/// Scan the raw process args for `--config <path>` / `--profile <name>`.
+///
+/// These need to be known before the `LINTERS` arg's `possible_values` can be
+/// computed, so they're pulled out ahead of building the full `clap::App`.
+fn preparse_config_args() -> (Option<PathBuf>, Option<String>) {
+    let args: Vec<String> = std::env::args().collect();
+    let config = preparse_arg_value(&args, "--config").map(PathBuf::from);
+    let profile = preparse_arg_value(&args, "--profile");
+    (config, profile)
+}
+
+/// Find the value for `flag`, accepting both the two-token (`--flag value`)
+/// and `=`-joined (`--flag=value`) forms clap itself supports
+fn preparse_arg_value(args: &[String], flag: &str) -> Option<String> {
+    let prefix = format!("{}=", flag);
+    args.iter()
+        .find_map(|arg| arg.strip_prefix(&prefix).map(|value| value.to_owned()))
+        .or_else(|| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|i| args.get(i + 1))
+                .cloned()
+        })
 }
 
 fn main() -> Result<(), Error> {
-    // Determine if a config file exists, otherwise create it
-    let xdg_dirs = xdg::BaseDirectories::with_prefix("lint-emit").unwrap();
-    let config_path = match xdg_dirs.find_config_file("config.toml") {
-        Some(file_path) => file_path,
+    let (config_override, profile_override) = preparse_config_args();
+
+    // When --config is given it bypasses the xdg lookup/interactive-creation
+    // flow entirely and is read from verbatim
+    let config_path = match config_override {
+        Some(path) => {
+            fs::canonicalize(&path)
+                .map_err(|_| failure::err_msg(format!("--config file {:?} does not exist", path)))?
+        },
         None => {
-            // Get the default config
-            let default_config: Config = toml::from_str(include_str!("default_config.toml"))?;
-            let linters = default_config.linters.unwrap();
-
-            // Prompt user to select linters
-            let linter_names: Vec<&str> = linters
-                .iter()
-                .map(|linter| linter.name.as_str())
-                .collect();
-
-            let selections = Checkboxes::with_theme(&ColorfulTheme::default())
-                .with_prompt("Choose linters [Press SPACE to select]")
-                .items(&linter_names)
-                .interact()
-                .unwrap();
-
-            let selected_names: Vec<&str> = selections
-                .into_iter()
-                .filter_map(|selection| linter_names.get(selection))
-                .map(|selection| *selection)
-                .collect();
-
-            let selected_linters: Vec<LinterConfig> = linters
-                .clone()
-                .into_iter()
-                .filter(|linter| {
-                    selected_names.contains(&linter.name.as_str())
-                })
-                .collect();
+            // Determine if a config file exists, otherwise create it
+            let xdg_dirs = xdg::BaseDirectories::with_prefix("lint-emit").unwrap();
+            match xdg_dirs.find_config_file("config.toml") {
+                Some(file_path) => file_path,
+                None => {
+                    // Get the default config
+                    let default_config: Config = toml::from_str(include_str!("default_config.toml"))?;
+                    let linters = default_config.linters.unwrap();
+
+                    // Prompt user to select linters
+                    let linter_names: Vec<&str> = linters
+                        .iter()
+                        .map(|linter| linter.name.as_str())
+                        .collect();
+
+                    let selections = Checkboxes::with_theme(&ColorfulTheme::default())
+                        .with_prompt("Choose linters [Press SPACE to select]")
+                        .items(&linter_names)
+                        .interact()
+                        .unwrap();
+
+                    let selected_names: Vec<&str> = selections
+                        .into_iter()
+                        .filter_map(|selection| linter_names.get(selection))
+                        .map(|selection| *selection)
+                        .collect();
+
+                    let selected_linters: Vec<LinterConfig> = linters
+                        .clone()
+                        .into_iter()
+                        .filter(|linter| {
+                            selected_names.contains(&linter.name.as_str())
+                        })
+                        .collect();
 
-            let new_config = Config {
-                linters: Some(selected_linters)
-            };
+                    let new_config = Config {
+                        linters: Some(selected_linters),
+                        profiles: None,
+                        filters: None
+                    };
 
-            // Create config file from selection
-            let config_path = xdg_dirs.place_config_file("config.toml")
-                                      .expect("Cannot create configuration directory");
+                    // Create config file from selection
+                    let config_path = xdg_dirs.place_config_file("config.toml")
+                                              .expect("Cannot create configuration directory");
 
-            let mut config_file = fs::File::create(config_path.clone())?;
-            write!(&mut config_file, "{}", toml::to_string(&new_config)?)?;
-            println!("Successfully wrote configuration file to {:?}", config_path);
+                    let mut config_file = fs::File::create(config_path.clone())?;
+                    write!(&mut config_file, "{}", toml::to_string(&new_config)?)?;
+                    println!("Successfully wrote configuration file to {:?}", config_path);
 
-            config_path
+                    config_path
+                }
+            }
         }
     };
 
     // Get the config
     let config_string = fs::read_to_string(config_path).expect("Unable to read file");
     let config: Config = toml::from_str(&config_string)?;
+    let filters = config.filters.clone().unwrap_or_default();
     let linters = config.linters.unwrap();
+
+    // Resolve a named profile to its subset of linters, when given
+    let linters = match &profile_override {
+        Some(profile_name) => {
+            let profiles = config.profiles.as_ref().expect("No [profiles] table in config");
+            let profile_linters = profiles.get(profile_name)
+                .unwrap_or_else(|| panic!("No such profile {:?}", profile_name));
+            linters
+                .into_iter()
+                .filter(|linter| profile_linters.contains(&linter.name))
+                .collect()
+        },
+        None => linters
+    };
+
     let possible_values: Vec<&str> = linters.iter().map(|linter| linter.name.as_str()).collect();
     let matches = App::new("lint-emit")
         .version("0.3")
@@ -139,10 +217,18 @@ fn main() -> Result<(), Error> {
         .setting(AppSettings::ColoredHelp)
         .arg(Arg::with_name("COMMIT_RANGE")
              .short("c")
-             .long("config")
+             .long("commit-range")
              .default_value("HEAD")
              .help("Commit range provided to diff")
              .index(1))
+        .arg(Arg::with_name("CONFIG")
+             .long("config")
+             .help("Load linters directly from this TOML file, bypassing the xdg config lookup")
+             .takes_value(true))
+        .arg(Arg::with_name("PROFILE")
+             .long("profile")
+             .help("Select a named [profiles] subset of linters from the config")
+             .takes_value(true))
         .arg(Arg::with_name("LINTERS")
              .short("l")
              .long("linters")
@@ -155,6 +241,42 @@ fn main() -> Result<(), Error> {
              .long("verbose")
              .help("Control the output verbosity")
              .multiple(true))
+        .arg(Arg::with_name("FORMAT")
+             .long("format")
+             .help("The output format for lint messages")
+             .possible_values(&["pretty", "json", "sarif", "github"])
+             .default_value("pretty")
+             .takes_value(true))
+        .arg(Arg::with_name("GITHUB_REVIEW")
+             .long("github-review")
+             .help("Post lint messages as a single GitHub PR review, using GITHUB_TOKEN"))
+        .arg(Arg::with_name("PR_NUMBER")
+             .long("pr-number")
+             .help("The PR number to review; defaults to parsing GITHUB_REF")
+             .takes_value(true))
+        .arg(Arg::with_name("NO_HIGHLIGHT")
+             .long("no-highlight")
+             .help("Disable syntax highlighting of source snippets"))
+        .arg(Arg::with_name("FAIL_LEVEL")
+             .long("fail-level")
+             .help("Exit non-zero when a message at or above this severity is found")
+             .possible_values(&["error", "warning", "info"])
+             .takes_value(true))
+        .arg(Arg::with_name("FAIL_FAST")
+             .long("fail-fast")
+             .help("Stop running linters, and further files, as soon as a qualifying message is found"))
+        .arg(Arg::with_name("FAIL_THRESHOLD")
+             .long("fail-threshold")
+             .help("Exit non-zero when more than this many lint messages are emitted")
+             .takes_value(true))
+        .arg(Arg::with_name("WATCH")
+             .long("watch")
+             .help("Keep running, re-linting whenever a matching file changes on disk"))
+        .arg(Arg::with_name("MIN_SEVERITY")
+             .long("min-severity")
+             .help("Suppress messages below this severity before rendering")
+             .possible_values(&["error", "warning", "info"])
+             .takes_value(true))
         .get_matches();
 
     // Setup logging level
@@ -206,12 +328,78 @@ fn main() -> Result<(), Error> {
         .collect();
     debug!(logger, "Linter Configs = {:#?}", linter_configs);
 
-    run(commit_range, linter_configs, logger)
+    let format = OutputFormat::from_str(matches.value_of("FORMAT").unwrap())
+        .expect("Invalid --format value");
+    let highlight = display::should_highlight(matches.is_present("NO_HIGHLIGHT"));
+    let fail_level = matches.value_of("FAIL_LEVEL")
+        .map(|level| Severity::from_str(level).expect("Invalid --fail-level value"));
+    let fail_fast = matches.is_present("FAIL_FAST");
+
+    let cache_xdg = xdg::BaseDirectories::with_prefix("lint-emit").unwrap();
+    let cache_path = cache_xdg.place_cache_file("cache.toml").expect("Cannot create cache directory");
+
+    let min_severity = matches.value_of("MIN_SEVERITY")
+        .map(|level| Severity::from_str(level).expect("Invalid --min-severity value"));
+
+    let mut do_run = || -> Result<Vec<LintMessage>, Error> {
+        run(commit_range, linter_configs.clone(), &filters, &logger, format, highlight, fail_fast, fail_level, min_severity, &cache_path)
+    };
+
+    if matches.is_present("WATCH") {
+        // --watch never returns out of the watch loop, so none of these
+        // one-shot, exit-code-driven flags ever get a chance to run
+        if matches.is_present("GITHUB_REVIEW") || fail_level.is_some() || matches.is_present("FAIL_THRESHOLD") {
+            warn!(logger, "--github-review, --fail-level and --fail-threshold have no effect under --watch");
+        }
+        let ext_filter: Vec<String> = linter_configs.iter().flat_map(|linter| linter.ext.clone()).collect();
+        return watch(&ext_filter, do_run);
+    }
+
+    let lint_messages = do_run()?;
+
+    if matches.is_present("GITHUB_REVIEW") {
+        let pr_number = matches.value_of("PR_NUMBER").map(|n| n.parse().expect("Invalid --pr-number value"));
+        github::post_review(&lint_messages, pr_number, &logger)?;
+    }
+
+    // Exit non-zero when a message at or above --fail-level was produced
+    if let Some(fail_level) = fail_level {
+        let failed = lint_messages.iter().any(|lint_message| lint_message.severity >= fail_level);
+        if failed {
+            std::process::exit(1);
+        }
+    }
+
+    // Exit non-zero when more than --fail-threshold messages were emitted
+    if let Some(fail_threshold) = matches.value_of("FAIL_THRESHOLD") {
+        let fail_threshold: usize = fail_threshold.parse().expect("Invalid --fail-threshold value");
+        if lint_messages.len() > fail_threshold {
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
 }
 
 /// Run the linters across the whole project and return the linting messages
 /// for just the changed lines
-fn run(commit_range: &str, linters: Vec<LinterConfig>, logger: slog::Logger) -> Result<(), Error> {
+fn run(
+    commit_range: &str,
+    linters: Vec<LinterConfig>,
+    filters: &Filters,
+    logger: &slog::Logger,
+    format: OutputFormat,
+    highlight: bool,
+    fail_fast: bool,
+    fail_level: Option<Severity>,
+    min_severity: Option<Severity>,
+    cache_path: &PathBuf
+) -> Result<Vec<LintMessage>, Error> {
+    let mut cache = Cache::load(cache_path);
+    cache.prune();
+    let cache = Arc::new(Mutex::new(cache));
+    // Without --fail-level, any message counts as qualifying for --fail-fast
+    let fail_level_for_linters = fail_level.unwrap_or(Severity::Info);
 
     let spinner = ProgressBar::new_spinner();
     spinner.enable_steady_tick(200);
@@ -221,17 +409,31 @@ fn run(commit_range: &str, linters: Vec<LinterConfig>, logger: slog::Logger) ->
     let changed_files = get_changed_files(commit_range)?;
     debug!(logger, "Changed Files = {:#?}", changed_files);
 
-    // Get the changed files and line numbers
+    // Get the changed lines for every file in a single `git diff` pass, rather
+    // than shelling out to `git diff` once per changed file
     spinner.set_message("Getting changed lines");
+    let mut all_changed_lines = get_all_changed_lines(commit_range)?;
     let diff_metas: Vec<DiffMeta> = changed_files
-        .par_iter()
-        .map(|file| get_changed_lines(commit_range, &file).unwrap())
+        .iter()
+        .filter_map(|file| all_changed_lines.remove(file))
+        .filter(|diff_meta| {
+            let path = display::repo_relative_path(&diff_meta.file);
+            let allowed = is_path_allowed(&path, &filters.include, &filters.exclude);
+            if !allowed {
+                debug!(logger, "Skipping {:?}, excluded by [filters]", path);
+            }
+            allowed
+        })
         .collect();
     trace!(logger, "Diff Metas = {:#?}", diff_metas);
     spinner.finish_and_clear();
 
     let pb = ProgressBar::new(diff_metas.len() as u64);
 
+    // Tripped once a file produces a message, when --fail-fast is set; stops
+    // further files from being linted
+    let stop_early = Arc::new(AtomicBool::new(false));
+
     // Get the output from running the linters for each file
     let lint_messages: Vec<LintMessage> = diff_metas
         .iter()
@@ -258,9 +460,54 @@ fn run(commit_range: &str, linters: Vec<LinterConfig>, logger: slog::Logger) ->
                         .collect::<Vec<&DiffMeta>>()
                         .par_iter()
                         .flat_map(|diff_meta| {
-                            let lint_messages = get_lint_messages_for_file(&diff_meta, &valid_linters, &logger);
+                            if fail_fast && stop_early.load(Ordering::Relaxed) {
+                                return vec![];
+                            }
+
+                            let path = display::repo_relative_path(&diff_meta.file);
+                            let file_linters: Vec<&LinterConfig> = valid_linters
+                                .iter()
+                                .filter(|linter| {
+                                    let allowed = is_path_allowed(&path, &linter.include, &linter.exclude);
+                                    if !allowed {
+                                        debug!(logger, "Skipping {:?} for linter {:?}, excluded by include/exclude", path, linter.name);
+                                    }
+                                    allowed
+                                })
+                                .map(|linter| *linter)
+                                .collect();
+
+                            // --fail-fast can make get_lint_messages_for_file return a
+                            // truncated list, so its results must never be read from or
+                            // written to the cache: either would poison a later,
+                            // non-fail-fast run with an incomplete result for this file
+                            let cache_key = if fail_fast {
+                                None
+                            } else {
+                                cache::cache_key(commit_range, &diff_meta, &file_linters).ok()
+                            };
+                            let cached = cache_key.as_ref()
+                                .and_then(|key| cache.lock().unwrap().get(key));
+                            let lint_messages = match cached {
+                                Some(lint_messages) => {
+                                    trace!(logger, "Cache hit for {:?}", diff_meta.file);
+                                    lint_messages
+                                },
+                                None => {
+                                    let lint_messages = get_lint_messages_for_file(&diff_meta, &file_linters, &logger, fail_fast, fail_level_for_linters);
+                                    if let Some(key) = cache_key {
+                                        cache.lock().unwrap().put(key, diff_meta.file.clone(), lint_messages.clone());
+                                    }
+                                    lint_messages
+                                }
+                            };
                             pb.println(format!("{} {}", "✓".green(), diff_meta.file.to_str().unwrap().dimmed()));
                             pb.inc(1);
+
+                            if fail_fast && lint_messages.iter().any(|m| m.severity >= fail_level_for_linters) {
+                                stop_early.store(true, Ordering::Relaxed);
+                            }
+
                             lint_messages
                         })
                         .collect::<Vec<LintMessage>>()
@@ -276,14 +523,70 @@ fn run(commit_range: &str, linters: Vec<LinterConfig>, logger: slog::Logger) ->
     trace!(logger, "Lint Messages = {:#?}", lint_messages);
     pb.finish_and_clear();
 
+    cache.lock().unwrap().save(cache_path)?;
+
+    // Suppress messages below --min-severity before rendering (and before
+    // they're handed to --github-review/--fail-level/--fail-threshold)
+    let lint_messages: Vec<LintMessage> = match min_severity {
+        Some(min_severity) => lint_messages
+            .into_iter()
+            .filter(|lint_message| lint_message.severity >= min_severity)
+            .collect(),
+        None => lint_messages
+    };
+
     // Output the result
-    display::render(lint_messages);
+    display::render(lint_messages.clone(), format, highlight);
 
-    Ok(())
+    Ok(lint_messages)
+}
+
+/// Re-run `do_run` (the lint pipeline) whenever a file matching one of the
+/// configured linter extensions changes on disk, debouncing rapid bursts of
+/// filesystem events. Clears the terminal between passes so it reads like a
+/// single live view rather than a scrollback of runs.
+fn watch(ext_filter: &[String], mut do_run: impl FnMut() -> Result<Vec<LintMessage>, Error>) -> Result<(), Error> {
+    use notify::{Watcher, RecursiveMode, DebouncedEvent, watcher};
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    do_run()?;
+
+    let (tx, rx) = channel();
+    let mut watcher = watcher(tx, Duration::from_millis(500))
+        .map_err(|e| failure::err_msg(e.to_string()))?;
+    watcher.watch(".", RecursiveMode::Recursive)
+        .map_err(|e| failure::err_msg(e.to_string()))?;
+
+    loop {
+        let event = rx.recv().map_err(|e| failure::err_msg(e.to_string()))?;
+        let changed_path = match event {
+            DebouncedEvent::Write(path) | DebouncedEvent::Create(path) => Some(path),
+            _ => None
+        };
+
+        let is_relevant = changed_path
+            .as_ref()
+            .and_then(|path| path.extension())
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext_filter.iter().any(|watched_ext| watched_ext == ext))
+            .unwrap_or(false);
+
+        if is_relevant {
+            print!("\x1B[2J\x1B[1;1H");
+            do_run()?;
+        }
+    }
 }
 
-fn get_lint_messages_for_file(diff_meta: &DiffMeta, linters: &Vec<&LinterConfig>, logger: &slog::Logger) -> Vec<LintMessage> {
-    let lint_messages = get_lint_messages(linters, &diff_meta, &logger);
+fn get_lint_messages_for_file(
+    diff_meta: &DiffMeta,
+    linters: &Vec<&LinterConfig>,
+    logger: &slog::Logger,
+    fail_fast: bool,
+    fail_level: Severity
+) -> Vec<LintMessage> {
+    let lint_messages = get_lint_messages(linters, &diff_meta, &logger, fail_fast, fail_level);
     match lint_messages {
         Ok(lint_messages) => lint_messages,
         Err(_) => panic!("Unable to find file {:?}", diff_meta.file)